@@ -0,0 +1,119 @@
+//! `zmng://` deep-link handling.
+//!
+//! Lets notification emails, webhooks, and other apps open this app
+//! directly onto a specific camera or recorded event, e.g.
+//! `zmng://monitor/7` or `zmng://event/123456?server=home`.
+//!
+//! This module is only the Rust-side half: parsing a `zmng://` URL and
+//! dispatching it, plus `tauri-plugin-single-instance` so a second
+//! launch on Windows/Linux hands its argv to the already-running
+//! process instead of starting a new one. The OS-level half — getting
+//! `zmng://` links routed to this app at all — is `tauri.conf.json`'s
+//! `plugins.deep-link.desktop.schemes`, which the Tauri bundler expands
+//! into the per-platform registration (Info.plist `CFBundleURLTypes` on
+//! macOS, the registry/NSIS install step on Windows, the `.desktop`
+//! `MimeType` entry on Linux).
+//!
+//! CLI-argv forwarding on Windows/Linux (a URL showing up as an argv
+//! entry on a second launch) is handled by enabling
+//! `tauri-plugin-single-instance`'s `deep-link` Cargo feature rather
+//! than hand-parsing argv here: with it on, the plugin forwards argv
+//! into `tauri_plugin_deep_link::handle_cli_arguments` itself, which
+//! raises the same `on_open_url` event [`register`] already listens for
+//! on macOS.
+
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+pub const SCHEME: &str = "zmng";
+
+/// Where a deep link should take the frontend.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeepLinkTarget {
+  Monitor { id: String, server: Option<String> },
+  Event { id: String, server: Option<String> },
+}
+
+/// Parses a `zmng://` URL into a navigation target.
+///
+/// Returns `None` for anything that isn't `monitor/<id>` or
+/// `event/<id>`, so malformed or future-version links are ignored
+/// instead of crashing the parser.
+fn parse(url: &str) -> Option<DeepLinkTarget> {
+  let url = url::Url::parse(url).ok()?;
+  if url.scheme() != SCHEME {
+    return None;
+  }
+
+  let server = url
+    .query_pairs()
+    .find(|(key, _)| key == "server")
+    .map(|(_, value)| value.into_owned());
+
+  let mut segments = url.host_str().into_iter().chain(url.path_segments()?);
+  match (segments.next(), segments.next()) {
+    (Some("monitor"), Some(id)) => Some(DeepLinkTarget::Monitor { id: id.to_string(), server }),
+    (Some("event"), Some(id)) => Some(DeepLinkTarget::Event { id: id.to_string(), server }),
+    _ => None,
+  }
+}
+
+/// Shows and focuses the main window, e.g. in response to a second
+/// launch or a deep link, so the app is visibly brought to the front
+/// instead of silently updating in the background.
+fn focus_main_window<R: Runtime>(app: &AppHandle<R>) {
+  if let Some(window) = app.get_webview_window("main") {
+    let _ = window.show();
+    let _ = window.set_focus();
+  }
+}
+
+/// Focuses the main window and emits a `deep-link://navigate` event to
+/// the frontend with the parsed target.
+fn dispatch<R: Runtime>(app: &AppHandle<R>, target: DeepLinkTarget) {
+  focus_main_window(app);
+  let _ = app.emit("deep-link://navigate", target);
+}
+
+/// Builds the `tauri-plugin-single-instance` plugin.
+///
+/// A second launch always focuses the existing window — that's the
+/// point of single-instance — whether or not its argv happened to carry
+/// a `zmng://` link. Forwarding a link that *is* present into
+/// [`register`]'s `on_open_url` handler is the `deep-link` Cargo
+/// feature's job (see the module doc comment), not this closure's; this
+/// only needs to handle focusing.
+///
+/// On macOS, a URL opened while the app is already running is delivered
+/// to it directly by the OS and surfaces through `on_open_url` in
+/// [`register`]. Windows and Linux just start a second process with the
+/// URL as an argv entry instead, so this is what actually stops that
+/// second process from running.
+///
+/// Must be the very first `.plugin()` registered on the builder in
+/// `lib.rs`'s `run()` — every plugin/setup hook registered before it
+/// (tray icon creation, log rotation, ...) would otherwise still run in
+/// a second launch before single-instance gets a chance to exit it.
+pub fn single_instance_plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+  tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+    focus_main_window(app);
+  })
+}
+
+/// Registers the deep-link plugin and wires cold-start/already-running
+/// URLs through to [`dispatch`]. [`single_instance_plugin`] is
+/// registered separately, first, in `lib.rs`.
+pub fn register<R: Runtime>(builder: tauri::Builder<R>) -> tauri::Builder<R> {
+  builder.plugin(tauri_plugin_deep_link::init()).setup(|app| {
+    let handle = app.handle().clone();
+    app.deep_link().on_open_url(move |event| {
+      for url in event.urls() {
+        if let Some(target) = parse(url.as_str()) {
+          dispatch(&handle, target);
+        }
+      }
+    });
+    Ok(())
+  })
+}
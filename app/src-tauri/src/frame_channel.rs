@@ -0,0 +1,275 @@
+//! Per-monitor streaming channel for decoded frame metadata and stats.
+//!
+//! Montage views with several cameras polling HTTP endpoints per frame
+//! generate a lot of redundant IPC traffic. This gives the frontend a
+//! single long-lived `Channel<FrameUpdate>` per monitor instead: the
+//! backend task owns the upstream ZM connection and pushes metadata
+//! (timestamp, alarm score, motion zones) plus periodic connection/FPS
+//! stats as they happen.
+//!
+//! `Channel::send` only fails once the whole webview/window is gone —
+//! not when the frontend simply stops listening to this particular
+//! channel (e.g. a montage tile unmounts while the rest of the app stays
+//! open) — so that alone can't be relied on to tear the relay down.
+//! `open_frame_channel` registers a cancellation signal for its channel
+//! id that `close_frame_channel` fires explicitly; the frontend is
+//! expected to call it when a tile unmounts.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::credentials;
+
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the active monitor's status is polled for new frame
+/// metadata.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How many non-alarm frames are allowed to queue before older ones are
+/// coalesced away; alarm frames always bypass this and are sent.
+const COALESCE_QUEUE_DEPTH: usize = 4;
+
+/// Close signals for currently-open channels, keyed by `Channel::id()`,
+/// so `close_frame_channel` can tear down a specific channel's relay
+/// task without waiting on `Channel::send` to notice the frontend is
+/// gone.
+#[derive(Default)]
+struct OpenChannels(Mutex<HashMap<u32, oneshot::Sender<()>>>);
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrameUpdate {
+  Frame { timestamp_ms: u64, alarm_score: u32, motion_zones: Vec<String> },
+  Stats { fps: f32, connected: bool },
+}
+
+struct DecodedFrame {
+  timestamp_ms: u64,
+  alarm_score: u32,
+  motion_zones: Vec<String>,
+}
+
+impl DecodedFrame {
+  fn is_alarm(&self) -> bool {
+    self.alarm_score > 0
+  }
+}
+
+/// Opens a streaming channel for `monitor_id` and starts the backend
+/// relay task.
+///
+/// The task owns the upstream connection for the lifetime of the
+/// channel: it coalesces non-alarm frames when the consumer falls
+/// behind (dropping intermediate frames, never alarm ones), emits FPS
+/// and connection stats once a second, and tears everything down once
+/// the webview/window itself is gone (`on_event.send` failing) or once
+/// `close_frame_channel` is called for this channel's id — which is the
+/// only signal for "the frontend stopped listening" while the rest of
+/// the app, and so the webview, is still very much alive.
+#[tauri::command]
+pub async fn open_frame_channel<R: Runtime>(
+  app: AppHandle<R>,
+  monitor_id: String,
+  server_id: Option<String>,
+  on_event: Channel<FrameUpdate>,
+) -> Result<(), String> {
+  let channel_id = on_event.id();
+  let (close_tx, mut close_rx) = oneshot::channel();
+  app.state::<OpenChannels>().0.lock().unwrap().insert(channel_id, close_tx);
+
+  let (upstream_tx, mut upstream_rx) = mpsc::channel::<DecodedFrame>(COALESCE_QUEUE_DEPTH);
+
+  tauri::async_runtime::spawn(relay_upstream(app.clone(), monitor_id, server_id, upstream_tx));
+
+  let mut frame_count: u32 = 0;
+  let mut stats_interval = tokio::time::interval(STATS_INTERVAL);
+
+  loop {
+    tokio::select! {
+      _ = &mut close_rx => break,
+      frame = upstream_rx.recv() => {
+        let Some(frame) = frame else {
+          // Upstream connection ended; let the frontend know and stop.
+          let _ = on_event.send(FrameUpdate::Stats { fps: 0.0, connected: false });
+          break;
+        };
+        frame_count += 1;
+        let update = FrameUpdate::Frame {
+          timestamp_ms: frame.timestamp_ms,
+          alarm_score: frame.alarm_score,
+          motion_zones: frame.motion_zones,
+        };
+        // A channel send failing means the whole webview/window is gone;
+        // tear the relay down rather than continuing to decode frames
+        // nobody can receive.
+        if on_event.send(update).is_err() {
+          break;
+        }
+      }
+      _ = stats_interval.tick() => {
+        let fps = frame_count as f32 / STATS_INTERVAL.as_secs_f32();
+        frame_count = 0;
+        if on_event.send(FrameUpdate::Stats { fps, connected: true }).is_err() {
+          break;
+        }
+      }
+    }
+  }
+
+  app.state::<OpenChannels>().0.lock().unwrap().remove(&channel_id);
+
+  Ok(())
+}
+
+#[derive(Deserialize)]
+struct MonitorStatusResponse {
+  monitor: MonitorStatus,
+}
+
+#[derive(Deserialize)]
+struct MonitorStatus {
+  #[serde(rename = "Status")]
+  status: MonitorStatusFields,
+}
+
+#[derive(Deserialize, Default)]
+struct MonitorStatusFields {
+  #[serde(default, rename = "Score")]
+  score: u32,
+  #[serde(default, rename = "MotionZones")]
+  motion_zones: Vec<String>,
+}
+
+/// Owns the upstream connection for the channel's lifetime: polls the
+/// monitor's current status on [`POLL_INTERVAL`] and pushes a
+/// [`DecodedFrame`] per poll, coalescing through [`send_coalesced`] so a
+/// slow consumer never backs up the poll loop. Exits (dropping `tx` and
+/// ending the channel) once credentials can no longer be resolved or
+/// the frontend has dropped its handle.
+async fn relay_upstream<R: Runtime>(
+  app: AppHandle<R>,
+  monitor_id: String,
+  server_id: Option<String>,
+  tx: mpsc::Sender<DecodedFrame>,
+) {
+  let mut interval = tokio::time::interval(POLL_INTERVAL);
+  loop {
+    interval.tick().await;
+    if tx.is_closed() {
+      return;
+    }
+
+    let Ok(resolved) = credentials::resolve_target(&app, server_id.as_deref()) else {
+      return;
+    };
+    let Ok(frame) = fetch_monitor_frame(&resolved, &monitor_id).await else {
+      continue;
+    };
+
+    if send_coalesced(&tx, frame).await.is_err() {
+      return;
+    }
+  }
+}
+
+/// Polls a single monitor's current status from the ZM API and turns it
+/// into a [`DecodedFrame`].
+async fn fetch_monitor_frame(
+  resolved: &credentials::ResolvedServer,
+  monitor_id: &str,
+) -> Result<DecodedFrame, reqwest::Error> {
+  let url = format!("{}/api/monitors/{monitor_id}.json", resolved.profile.url);
+  let client = credentials::pinned_client(resolved.profile.pinned_cert_fingerprint.as_deref())?;
+  let response: MonitorStatusResponse = client.get(&url).bearer_auth(&resolved.token).send().await?.json().await?;
+
+  Ok(DecodedFrame {
+    timestamp_ms: std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_millis() as u64,
+    alarm_score: response.monitor.status.score,
+    motion_zones: response.monitor.status.motion_zones,
+  })
+}
+
+/// Pushes `frame` onto the relay channel. Alarm frames always wait for
+/// room so they're never silently dropped; a non-alarm frame is instead
+/// dropped outright when the consumer hasn't drained the queue, since
+/// fresher non-alarm state is always coming on the next poll anyway.
+///
+/// Returns `Err` only when the receiving end (the frontend's channel
+/// handle) is gone, signaling the caller to stop polling.
+async fn send_coalesced(tx: &mpsc::Sender<DecodedFrame>, frame: DecodedFrame) -> Result<(), ()> {
+  if frame.is_alarm() {
+    tx.send(frame).await.map_err(|_| ())
+  } else {
+    match tx.try_send(frame) {
+      Ok(()) | Err(mpsc::error::TrySendError::Full(_)) => Ok(()),
+      Err(mpsc::error::TrySendError::Closed(_)) => Err(()),
+    }
+  }
+}
+
+/// Tears down the relay task for a channel opened via
+/// `open_frame_channel`, e.g. when a montage tile unmounts but the rest
+/// of the app (and so the webview) stays open.
+///
+/// A no-op if the channel has already closed on its own (upstream ended,
+/// or the whole webview/window went away first).
+#[tauri::command]
+pub fn close_frame_channel<R: Runtime>(app: AppHandle<R>, channel_id: u32) {
+  if let Some(close_tx) = app.state::<OpenChannels>().0.lock().unwrap().remove(&channel_id) {
+    let _ = close_tx.send(());
+  }
+}
+
+/// Registers the [`OpenChannels`] registry `close_frame_channel` needs
+/// to find a given channel's relay task; `open_frame_channel` and
+/// `close_frame_channel` themselves are wired into the shared invoke
+/// handler in `lib.rs`.
+pub fn register<R: Runtime>(builder: tauri::Builder<R>) -> tauri::Builder<R> {
+  builder.setup(|app| {
+    app.manage(OpenChannels::default());
+    Ok(())
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn frame(alarm_score: u32) -> DecodedFrame {
+    DecodedFrame { timestamp_ms: 0, alarm_score, motion_zones: Vec::new() }
+  }
+
+  #[tokio::test]
+  async fn drops_non_alarm_frame_when_queue_is_full() {
+    let (tx, mut rx) = mpsc::channel(1);
+    tx.try_send(frame(0)).unwrap();
+
+    assert!(send_coalesced(&tx, frame(0)).await.is_ok());
+    assert_eq!(rx.try_recv().unwrap().alarm_score, 0);
+    assert!(rx.try_recv().is_err());
+  }
+
+  #[tokio::test]
+  async fn never_drops_an_alarm_frame() {
+    let (tx, mut rx) = mpsc::channel(1);
+    tx.try_send(frame(0)).unwrap();
+
+    let send = tokio::spawn({
+      let tx = tx.clone();
+      async move { send_coalesced(&tx, frame(42)).await }
+    });
+    // The queued non-alarm frame has to be drained before the alarm
+    // frame's blocking send can complete.
+    assert_eq!(rx.recv().await.unwrap().alarm_score, 0);
+    assert!(send.await.unwrap().is_ok());
+    assert_eq!(rx.recv().await.unwrap().alarm_score, 42);
+  }
+}
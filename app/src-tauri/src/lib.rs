@@ -1,14 +1,68 @@
+mod credentials;
+mod deep_link;
+mod frame_channel;
+mod stream_proxy;
+mod tray;
+mod updater;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  tauri::Builder::default()
+  let builder = tauri::Builder::default()
+    // Must come before every other plugin/setup hook: on a second
+    // launch, this is what exits the process before any of them (tray
+    // icon creation, log rotation, ...) get a chance to run.
+    .plugin(deep_link::single_instance_plugin())
+    // Generic outbound fetch for the frontend, e.g. loading release notes
+    // or other non-ZM resources. It has no notion of the credential
+    // store, so `capabilities/default.json` scopes its `http:default`
+    // permission down to the release-update host — a ZM server URL (only
+    // known at runtime, never a build-time constant) is never in scope,
+    // so ZM API calls have no choice but to go through
+    // `credentials::api_request` instead, which actually attaches the
+    // stored token and honors the pinned fingerprint.
     .plugin(tauri_plugin_http::init())
+    .plugin(tauri_plugin_notification::init())
     .plugin(
       tauri_plugin_log::Builder::default()
         .level(log::LevelFilter::Info)
         .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepOne)
         .max_file_size(10 * 1024 * 1024)
         .build(),
-    )
+    );
+
+  let builder = credentials::register(builder);
+  // Resolves the active server (and its token/pinned fingerprint) from
+  // the credential store on every request, so it always reflects
+  // whatever the user has saved/switched to.
+  let builder = stream_proxy::register(builder);
+  let builder = deep_link::register(builder);
+  let builder = updater::register(builder);
+  let builder = frame_channel::register(builder);
+
+  // The monitor list itself is fetched from the active server profile at
+  // startup (see tray::register). This is just the initial filter state
+  // — an empty `enabled_monitor_ids` means "every monitor enabled" — and
+  // it's runtime-editable from here on via the `set_alert_filter`
+  // command once the frontend has a settings screen for it.
+  let builder = tray::register(builder, tray::AlertFilter {
+    enabled_monitor_ids: Vec::new(),
+    minimum_score: 50,
+    minimum_duration_secs: 3,
+  });
+
+  builder
+    .invoke_handler(tauri::generate_handler![
+      updater::check_for_updates,
+      updater::restart_to_install,
+      frame_channel::open_frame_channel,
+      frame_channel::close_frame_channel,
+      credentials::save_server,
+      credentials::list_servers,
+      credentials::delete_server,
+      credentials::get_token,
+      credentials::api_request,
+      tray::set_alert_filter,
+    ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
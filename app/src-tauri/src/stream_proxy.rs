@@ -0,0 +1,239 @@
+//! Custom `zmstream://` URI scheme that proxies ZoneMinder MJPEG/event
+//! streams into the webview.
+//!
+//! The webview can't fetch ZM streams directly: auth tokens need to be
+//! injected and TLS certs are often self-signed. Tauri's async URI
+//! scheme responder only supports a single fully-buffered response body
+//! per request (there's no API for incrementally pushing bytes into an
+//! already-returned response), so this can't hold the upstream
+//! `multipart/x-mixed-replace` connection open and relay it part by
+//! part. Instead each `zmstream://monitor/7` request opens a fresh
+//! upstream connection, grabs the next JPEG part, and returns it as a
+//! plain `image/jpeg` response; the frontend drives the "live" feed by
+//! re-requesting with a cache-busting query param on an interval, the
+//! same way a plain `<img>` MJPEG fallback would. Decoded frame
+//! metadata/stats for montage views go through [`crate::frame_channel`]
+//! instead, which *can* stay open for the connection's lifetime.
+use http::{Request, Response};
+use tauri::{AppHandle, Runtime, UriSchemeContext};
+
+use crate::credentials;
+
+pub const SCHEME: &str = "zmstream";
+
+/// Upstream ZM connection details needed to open a monitor stream.
+#[derive(Clone)]
+pub struct StreamTarget {
+  pub base_url: String,
+  pub auth_token: Option<String>,
+  pub pinned_cert_fingerprint: Option<String>,
+}
+
+impl From<credentials::ResolvedServer> for StreamTarget {
+  fn from(resolved: credentials::ResolvedServer) -> Self {
+    StreamTarget {
+      base_url: resolved.profile.url,
+      auth_token: Some(resolved.token),
+      pinned_cert_fingerprint: resolved.profile.pinned_cert_fingerprint,
+    }
+  }
+}
+
+/// Parses the `monitor/<id>` path and optional `?server=` id out of a
+/// `zmstream://` request, e.g. `zmstream://monitor/7?server=home`.
+fn monitor_request_from_uri(uri: &str) -> Option<(String, Option<String>)> {
+  let without_scheme = uri.strip_prefix("zmstream://")?;
+  let mut segments = without_scheme.trim_start_matches('/').splitn(2, '/');
+  let (Some("monitor"), Some(rest)) = (segments.next(), segments.next()) else {
+    return None;
+  };
+
+  let mut parts = rest.splitn(2, '?');
+  let monitor_id = parts.next()?.split('#').next().unwrap_or_default().to_string();
+  let server_id = parts
+    .next()
+    .and_then(|query| url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == "server"))
+    .map(|(_, value)| value.into_owned());
+
+  Some((monitor_id, server_id))
+}
+
+/// Registers the `zmstream://` protocol on the builder.
+///
+/// Each request resolves the target server from the saved credential
+/// store (so the stored token and pinned fingerprint are always
+/// current), opens a fresh connection to it, and returns the next JPEG
+/// frame as a buffered `image/jpeg` response.
+pub fn register<R: Runtime>(builder: tauri::Builder<R>) -> tauri::Builder<R> {
+  builder.register_asynchronous_uri_scheme_protocol(SCHEME, move |ctx: UriSchemeContext<R>, request, responder| {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+      match proxy_stream(&app, request).await {
+        Ok(response) => responder.respond(response),
+        Err(err) => responder.respond(
+          Response::builder()
+            .status(502)
+            .body(format!("zmstream proxy error: {err}").into_bytes())
+            .unwrap(),
+        ),
+      }
+    });
+  })
+}
+
+/// Opens the upstream monitor stream just long enough to pull a single
+/// JPEG frame out of it and returns that frame as the response body.
+async fn proxy_stream<R: Runtime>(
+  app: &AppHandle<R>,
+  request: Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, StreamProxyError> {
+  let (monitor_id, server_id) =
+    monitor_request_from_uri(&request.uri().to_string()).ok_or(StreamProxyError::InvalidUri)?;
+
+  let resolved = credentials::resolve_target(app, server_id.as_deref())
+    .map_err(|_| StreamProxyError::MissingCredentials)?;
+  let target = StreamTarget::from(resolved);
+
+  let client = build_client(&target)?;
+  let upstream_url = format!("{}/cgi-bin/nph-zms?mode=jpeg&monitor={monitor_id}", target.base_url);
+
+  let mut req = client.get(&upstream_url);
+  if let Some(token) = &target.auth_token {
+    req = req.bearer_auth(token);
+  }
+
+  let upstream = req.send().await.map_err(StreamProxyError::Upstream)?;
+  let upstream_boundary = boundary_from_content_type(upstream.headers().get("content-type"))
+    .ok_or(StreamProxyError::UnrecognizedUpstreamBody)?;
+
+  let jpeg_bytes = fetch_first_jpeg_part(upstream, &upstream_boundary).await?;
+
+  Ok(
+    Response::builder()
+      .status(200)
+      .header("Content-Type", "image/jpeg")
+      // Every response is a fresh frame, never the previous one.
+      .header("Cache-Control", "no-store")
+      .header("Access-Control-Allow-Origin", "*")
+      .body(jpeg_bytes)
+      .unwrap(),
+  )
+}
+
+/// Builds the HTTP client used for a single monitor stream, pinned to
+/// `target`'s fingerprint when it has one.
+///
+/// Delegates to [`credentials::pinned_client`], the same pinning logic
+/// the credential store's generic `api_request` command uses, so a
+/// pinned fingerprint is honored identically everywhere in the backend.
+fn build_client(target: &StreamTarget) -> Result<reqwest::Client, StreamProxyError> {
+  credentials::pinned_client(target.pinned_cert_fingerprint.as_deref()).map_err(StreamProxyError::ClientBuild)
+}
+
+/// Extracts the `boundary=` parameter from an upstream
+/// `multipart/x-mixed-replace` content-type header.
+fn boundary_from_content_type(header: Option<&http::HeaderValue>) -> Option<String> {
+  let value = header?.to_str().ok()?;
+  value
+    .split(';')
+    .find_map(|part| part.trim().strip_prefix("boundary="))
+    .map(|boundary| boundary.trim_matches('"').to_string())
+}
+
+/// Pulls chunks off `upstream` until a complete boundary-delimited part
+/// has arrived, then returns just that part's JPEG payload. Gives up
+/// once the upstream connection ends without ever completing a part.
+async fn fetch_first_jpeg_part(mut upstream: reqwest::Response, boundary: &str) -> Result<Vec<u8>, StreamProxyError> {
+  let mut buffer = Vec::new();
+  loop {
+    if let Some(part) = take_next_part(&mut buffer, boundary) {
+      return Ok(jpeg_payload(&part).to_vec());
+    }
+    match upstream.chunk().await.map_err(StreamProxyError::Upstream)? {
+      Some(chunk) => buffer.extend_from_slice(&chunk),
+      None => return Err(StreamProxyError::UnrecognizedUpstreamBody),
+    }
+  }
+}
+
+/// Pulls the next complete boundary-delimited part out of `buffer`,
+/// leaving any trailing partial data for the next chunk.
+fn take_next_part(buffer: &mut Vec<u8>, boundary: &str) -> Option<Vec<u8>> {
+  let marker = format!("--{boundary}").into_bytes();
+  let first = find_subsequence(buffer, &marker)?;
+  let after_first = first + marker.len();
+  let second = find_subsequence(&buffer[after_first..], &marker)? + after_first;
+  let part = buffer[after_first..second].to_vec();
+  buffer.drain(..second);
+  Some(part)
+}
+
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Strips a raw multipart part's per-part headers and the trailing
+/// `\r\n` that precedes the next boundary marker, leaving just the JPEG
+/// bytes.
+fn jpeg_payload(part: &[u8]) -> &[u8] {
+  let jpeg_start = find_subsequence(part, b"\r\n\r\n").map(|idx| idx + 4).unwrap_or(0);
+  part[jpeg_start..].strip_suffix(b"\r\n").unwrap_or(&part[jpeg_start..])
+}
+
+#[derive(Debug, thiserror::Error)]
+enum StreamProxyError {
+  #[error("invalid zmstream:// uri, expected zmstream://monitor/<id>")]
+  InvalidUri,
+  #[error("no saved server credentials available for this stream")]
+  MissingCredentials,
+  #[error("failed to build upstream http client: {0}")]
+  ClientBuild(reqwest::Error),
+  #[error("upstream zoneminder request failed: {0}")]
+  Upstream(reqwest::Error),
+  #[error("upstream response was not a recognizable multipart stream")]
+  UnrecognizedUpstreamBody,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_monitor_id() {
+    assert_eq!(
+      monitor_request_from_uri("zmstream://monitor/7"),
+      Some(("7".to_string(), None))
+    );
+  }
+
+  #[test]
+  fn parses_monitor_id_with_server() {
+    assert_eq!(
+      monitor_request_from_uri("zmstream://monitor/7?server=home"),
+      Some(("7".to_string(), Some("home".to_string())))
+    );
+  }
+
+  #[test]
+  fn rejects_non_monitor_path() {
+    assert_eq!(monitor_request_from_uri("zmstream://event/7"), None);
+  }
+
+  #[test]
+  fn extracts_boundary_from_content_type() {
+    let header = http::HeaderValue::from_static("multipart/x-mixed-replace; boundary=ZoneMinderFrame");
+    assert_eq!(boundary_from_content_type(Some(&header)), Some("ZoneMinderFrame".to_string()));
+  }
+
+  #[test]
+  fn strips_part_headers_and_trailing_crlf() {
+    let raw = b"Content-Type: image/jpeg\r\nContent-Length: 3\r\n\r\nabc\r\n";
+    assert_eq!(jpeg_payload(raw), b"abc");
+  }
+
+  #[test]
+  fn strips_headers_only_when_no_trailing_crlf() {
+    let raw = b"Content-Type: image/jpeg\r\nContent-Length: 3\r\n\r\nabc";
+    assert_eq!(jpeg_payload(raw), b"abc");
+  }
+}
@@ -0,0 +1,405 @@
+//! System-tray presence.
+//!
+//! Keeps the app resident with a menu listing configured monitors, a
+//! show/hide toggle, and quit. A background task polls the ZoneMinder
+//! event API and, when a new alarm/event passes the configured filter,
+//! flashes the tray icon and raises an OS notification whose click
+//! navigates the main window to the triggering monitor.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::credentials;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How long the alert icon stays up before reverting to the app's
+/// default tray icon — a flash, not a permanent state change.
+const ALERT_ICON_FLASH_DURATION: Duration = Duration::from_secs(5);
+
+/// A configured ZM monitor as surfaced in the tray menu.
+#[derive(Clone)]
+pub struct MonitorHandle {
+  pub id: String,
+  pub name: String,
+}
+
+/// Per-monitor alert filtering: suppresses notifications for monitors
+/// the user doesn't want flashing the tray, and for events too short or
+/// low-scoring to matter. Runtime-editable via the `set_alert_filter`
+/// command (wired into `lib.rs`'s invoke handler).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AlertFilter {
+  pub enabled_monitor_ids: Vec<String>,
+  pub minimum_score: u32,
+  pub minimum_duration_secs: u32,
+}
+
+impl AlertFilter {
+  /// An empty `enabled_monitor_ids` means "every monitor is enabled" —
+  /// both the out-of-the-box default (before the user has narrowed
+  /// anything down) and an explicit "no per-monitor filtering" choice,
+  /// rather than a filter that can never pass.
+  fn allows(&self, event: &ZmEvent) -> bool {
+    (self.enabled_monitor_ids.is_empty() || self.enabled_monitor_ids.contains(&event.monitor_id))
+      && event.score >= self.minimum_score
+      && event.duration_secs >= self.minimum_duration_secs
+  }
+}
+
+/// Shared, runtime-updatable [`AlertFilter`], managed via `app.manage()`
+/// so `set_alert_filter` can change it without restarting the poll loop.
+struct AlertFilterState(Mutex<AlertFilter>);
+
+/// Replaces the active alert filter, e.g. from a settings screen letting
+/// the user toggle which monitors raise tray alerts.
+#[tauri::command]
+pub fn set_alert_filter<R: Runtime>(app: AppHandle<R>, filter: AlertFilter) {
+  *app.state::<AlertFilterState>().0.lock().unwrap() = filter;
+}
+
+/// Minimal event shape polled from the ZM events API.
+struct ZmEvent {
+  id: String,
+  monitor_id: String,
+  monitor_name: String,
+  score: u32,
+  duration_secs: u32,
+}
+
+/// Tracks which event IDs have already been alerted on, per monitor, so
+/// a slow poll loop doesn't re-notify for the same event.
+#[derive(Default)]
+struct SeenEvents(Mutex<HashMap<String, String>>);
+
+impl SeenEvents {
+  fn is_new(&self, event: &ZmEvent) -> bool {
+    let mut seen = self.0.lock().unwrap();
+    let is_new = seen.get(&event.monitor_id) != Some(&event.id);
+    seen.insert(event.monitor_id.clone(), event.id.clone());
+    is_new
+  }
+}
+
+/// Builds the tray menu: "Show/Hide", one item per monitor, then "Quit".
+fn build_menu<R: Runtime>(app: &AppHandle<R>, monitors: &[MonitorHandle]) -> tauri::Result<Menu<R>> {
+  let toggle = MenuItem::with_id(app, "toggle-window", "Show/Hide zmNg", true, None::<&str>)?;
+  let separator = PredefinedMenuItem::separator(app)?;
+  let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+
+  let mut items: Vec<&dyn tauri::menu::IsMenuItem<R>> = vec![&toggle, &separator];
+  let monitor_items: Vec<MenuItem<R>> = monitors
+    .iter()
+    .map(|monitor| MenuItem::with_id(app, format!("monitor-{}", monitor.id), &monitor.name, true, None::<&str>))
+    .collect::<Result<_, _>>()?;
+  for item in &monitor_items {
+    items.push(item);
+  }
+  items.push(&separator);
+  items.push(&quit);
+
+  Menu::with_items(app, &items)
+}
+
+/// Handles a tray menu selection: toggling the window, focusing a
+/// monitor, or quitting the app.
+fn handle_menu_event<R: Runtime>(app: &AppHandle<R>, id: &str) {
+  match id {
+    "quit" => app.exit(0),
+    "toggle-window" => {
+      if let Some(window) = app.get_webview_window("main") {
+        let visible = window.is_visible().unwrap_or(false);
+        if visible {
+          let _ = window.hide();
+          set_accessory_activation_policy(app, true);
+        } else {
+          let _ = window.show();
+          let _ = window.set_focus();
+          set_accessory_activation_policy(app, false);
+        }
+      }
+    }
+    other => {
+      if let Some(monitor_id) = other.strip_prefix("monitor-") {
+        if let Some(window) = app.get_webview_window("main") {
+          let _ = window.show();
+          let _ = window.set_focus();
+        }
+        let _ = app.emit("tray://focus-monitor", monitor_id);
+      }
+    }
+  }
+}
+
+/// On macOS, switch to an accessory (no dock icon) activation policy
+/// while the main window is hidden, so the tray presence can run as a
+/// pure background watcher; switch back to regular once it's shown.
+#[cfg(target_os = "macos")]
+fn set_accessory_activation_policy<R: Runtime>(app: &AppHandle<R>, hidden: bool) {
+  use tauri::ActivationPolicy;
+  let policy = if hidden { ActivationPolicy::Accessory } else { ActivationPolicy::Regular };
+  // Only fails if the platform refuses the policy switch outright; there's
+  // no fallback policy to retry with, so there's nothing more useful to do
+  // than let it go and keep whatever policy is already in effect.
+  let _ = app.set_activation_policy(policy);
+}
+
+#[cfg(not(target_os = "macos"))]
+fn set_accessory_activation_policy<R: Runtime>(_app: &AppHandle<R>, _hidden: bool) {}
+
+/// Polls the ZM events API on [`POLL_INTERVAL`] and raises a tray flash
+/// plus OS notification for each new event that passes the current
+/// [`AlertFilterState`] (re-read every event, so a `set_alert_filter`
+/// call takes effect on the next poll without restarting this loop).
+///
+/// `since_event_id` is only known starting with the *second* poll — the
+/// first poll after every launch has no baseline and would otherwise
+/// pull the server's entire unbounded events index and notify on all of
+/// it. So the first poll only seeds `last_event_id` and `seen`; alerting
+/// starts on the second poll onward, once there's an actual baseline to
+/// diff against.
+///
+/// Only non-alarm bookkeeping is skipped on a slow consumer; every event
+/// that clears the filter is still notified, since this is the alerting
+/// path and must never silently drop an alarm.
+async fn poll_events<R: Runtime>(app: AppHandle<R>, tray: TrayIcon<R>) {
+  let seen = SeenEvents::default();
+  let mut last_event_id: Option<String> = None;
+  // Captured once so a flash can revert to whatever icon was actually in
+  // place before it, rather than assuming a fixed default.
+  let default_icon = app.default_window_icon().cloned();
+
+  loop {
+    tokio::time::sleep(POLL_INTERVAL).await;
+
+    let is_first_poll = last_event_id.is_none();
+
+    let Ok(resolved) = credentials::resolve_target(&app, None) else { continue };
+    let Ok(events) = fetch_latest_events(&resolved, last_event_id.as_deref()).await else { continue };
+
+    if let Some(newest) = events.first() {
+      last_event_id = Some(newest.id.clone());
+    }
+
+    for event in events {
+      let is_new = seen.is_new(&event);
+      if is_first_poll {
+        continue;
+      }
+
+      let filter = app.state::<AlertFilterState>().0.lock().unwrap().clone();
+      if !filter.allows(&event) || !is_new {
+        continue;
+      }
+
+      let _ = tray.set_icon(Some(
+        tauri::image::Image::from_bytes(include_bytes!("../icons/tray-alert.png")).unwrap(),
+      ));
+      schedule_icon_reset(tray.clone(), default_icon.clone());
+
+      let _ = app
+        .notification()
+        .builder()
+        .title(format!("Motion on {}", event.monitor_name))
+        .body(format!("Event {} detected", event.id))
+        .show();
+
+      let _ = app.emit("tray://alert", event.monitor_id.clone());
+    }
+  }
+}
+
+/// Reverts `tray`'s icon back to `default_icon` after
+/// [`ALERT_ICON_FLASH_DURATION`], so the alert icon set in [`poll_events`]
+/// reads as a flash instead of a permanent change.
+fn schedule_icon_reset<R: Runtime>(tray: TrayIcon<R>, default_icon: Option<tauri::image::Image<'static>>) {
+  tauri::async_runtime::spawn(async move {
+    tokio::time::sleep(ALERT_ICON_FLASH_DURATION).await;
+    let _ = tray.set_icon(default_icon);
+  });
+}
+
+#[derive(Deserialize)]
+struct EventsIndexResponse {
+  events: Vec<EventEntry>,
+}
+
+#[derive(Deserialize)]
+struct EventEntry {
+  #[serde(rename = "Event")]
+  event: EventFields,
+}
+
+#[derive(Deserialize)]
+struct EventFields {
+  #[serde(rename = "Id")]
+  id: String,
+  #[serde(rename = "MonitorId")]
+  monitor_id: String,
+  #[serde(rename = "MonitorName", default)]
+  monitor_name: String,
+  #[serde(rename = "MaxScore", default)]
+  max_score: u32,
+  #[serde(rename = "Length", default)]
+  length_secs: u32,
+}
+
+/// Fetches events newer than `since_event_id` (all of them, on the
+/// first poll) from the resolved server's events API, most recent
+/// first.
+async fn fetch_latest_events(
+  resolved: &credentials::ResolvedServer,
+  since_event_id: Option<&str>,
+) -> Result<Vec<ZmEvent>, reqwest::Error> {
+  let mut url = format!("{}/api/events/index.json?sort=Event.Id&direction=desc", resolved.profile.url);
+  if let Some(since) = since_event_id {
+    url.push_str(&format!("&Event.Id >gt:{since}"));
+  }
+
+  let client = credentials::pinned_client(resolved.profile.pinned_cert_fingerprint.as_deref())?;
+  let response: EventsIndexResponse = client.get(&url).bearer_auth(&resolved.token).send().await?.json().await?;
+
+  Ok(
+    response
+      .events
+      .into_iter()
+      .map(|entry| ZmEvent {
+        id: entry.event.id,
+        monitor_id: entry.event.monitor_id,
+        monitor_name: entry.event.monitor_name,
+        score: entry.event.max_score,
+        duration_secs: entry.event.length_secs,
+      })
+      .collect(),
+  )
+}
+
+#[derive(Deserialize)]
+struct MonitorsIndexResponse {
+  monitors: Vec<MonitorEntry>,
+}
+
+#[derive(Deserialize)]
+struct MonitorEntry {
+  #[serde(rename = "Monitor")]
+  monitor: MonitorFields,
+}
+
+#[derive(Deserialize)]
+struct MonitorFields {
+  #[serde(rename = "Id")]
+  id: String,
+  #[serde(rename = "Name")]
+  name: String,
+}
+
+/// Fetches the configured monitor list from the resolved server, for
+/// populating the tray menu.
+async fn fetch_monitors(resolved: &credentials::ResolvedServer) -> Result<Vec<MonitorHandle>, reqwest::Error> {
+  let url = format!("{}/api/monitors.json", resolved.profile.url);
+  let client = credentials::pinned_client(resolved.profile.pinned_cert_fingerprint.as_deref())?;
+  let response: MonitorsIndexResponse = client.get(&url).bearer_auth(&resolved.token).send().await?.json().await?;
+
+  Ok(
+    response
+      .monitors
+      .into_iter()
+      .map(|entry| MonitorHandle { id: entry.monitor.id, name: entry.monitor.name })
+      .collect(),
+  )
+}
+
+/// Registers the tray icon, menu, and background alert poller.
+///
+/// The menu starts out with just "Show/Hide" and "Quit"; the monitor
+/// list is filled in once the active server profile's monitors have
+/// been fetched, since that requires the credential store (and a
+/// network round-trip) that isn't available synchronously at setup
+/// time.
+pub fn register<R: Runtime>(builder: tauri::Builder<R>, filter: AlertFilter) -> tauri::Builder<R> {
+  builder.setup(move |app| {
+    app.manage(AlertFilterState(Mutex::new(filter.clone())));
+    let handle = app.handle().clone();
+    let menu = build_menu(app.handle(), &[])?;
+
+    let tray = TrayIconBuilder::new()
+      .menu(&menu)
+      .show_menu_on_left_click(true)
+      .on_menu_event(move |app, event| handle_menu_event(app, event.id.as_ref()))
+      .on_tray_icon_event(|tray, event| {
+        if let TrayIconEvent::Click { .. } = event {
+          if let Some(app) = tray.app_handle().get_webview_window("main") {
+            let _ = app.show();
+            let _ = app.set_focus();
+          }
+        }
+      })
+      .build(app)?;
+
+    tauri::async_runtime::spawn({
+      let app = handle.clone();
+      let tray = tray.clone();
+      async move {
+        if let Ok(resolved) = credentials::resolve_target(&app, None) {
+          if let Ok(monitors) = fetch_monitors(&resolved).await {
+            if let Ok(menu) = build_menu(&app, &monitors) {
+              let _ = tray.set_menu(Some(menu));
+            }
+          }
+        }
+      }
+    });
+
+    tauri::async_runtime::spawn(poll_events(handle, tray));
+    Ok(())
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn event(monitor_id: &str, id: &str, score: u32, duration_secs: u32) -> ZmEvent {
+    ZmEvent { id: id.to_string(), monitor_id: monitor_id.to_string(), monitor_name: String::new(), score, duration_secs }
+  }
+
+  #[test]
+  fn allows_event_on_enabled_monitor_clearing_thresholds() {
+    let filter = AlertFilter { enabled_monitor_ids: vec!["1".to_string()], minimum_score: 10, minimum_duration_secs: 5 };
+    assert!(filter.allows(&event("1", "100", 10, 5)));
+  }
+
+  #[test]
+  fn rejects_event_on_disabled_monitor() {
+    let filter = AlertFilter { enabled_monitor_ids: vec!["1".to_string()], minimum_score: 0, minimum_duration_secs: 0 };
+    assert!(!filter.allows(&event("2", "100", 100, 100)));
+  }
+
+  #[test]
+  fn empty_enabled_monitor_ids_allows_every_monitor() {
+    let filter = AlertFilter { enabled_monitor_ids: Vec::new(), minimum_score: 0, minimum_duration_secs: 0 };
+    assert!(filter.allows(&event("1", "100", 0, 0)));
+    assert!(filter.allows(&event("2", "101", 0, 0)));
+  }
+
+  #[test]
+  fn rejects_event_below_score_or_duration_threshold() {
+    let filter = AlertFilter { enabled_monitor_ids: vec!["1".to_string()], minimum_score: 10, minimum_duration_secs: 5 };
+    assert!(!filter.allows(&event("1", "100", 9, 5)));
+    assert!(!filter.allows(&event("1", "100", 10, 4)));
+  }
+
+  #[test]
+  fn seen_events_is_new_only_once_per_monitor() {
+    let seen = SeenEvents::default();
+    assert!(seen.is_new(&event("1", "100", 0, 0)));
+    assert!(!seen.is_new(&event("1", "100", 0, 0)));
+    assert!(seen.is_new(&event("1", "101", 0, 0)));
+  }
+}
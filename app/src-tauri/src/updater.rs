@@ -0,0 +1,340 @@
+//! Self-update subsystem.
+//!
+//! ZM client installs that live outside an app store (plain downloaded
+//! binaries, sideloaded Linux packages) have no platform-level update
+//! mechanism, so the app checks a signed manifest itself: fetch it on
+//! startup and on a user-triggered "Check for updates" command, compare
+//! semver against the running build, and — if newer — download and
+//! verify the archive against a compile-time-embedded ed25519 (minisign)
+//! public key before it ever touches disk. The release artifact is a
+//! single-entry zip wrapping the platform executable (the same shape the
+//! release pipeline produces for all three platforms), so installing
+//! unpacks that entry before handing it to `self_replace`: once the user
+//! accepts the restart prompt, the unpacked executable replaces the
+//! running one in place (there's no separate platform installer to hand
+//! it to) before the app restarts.
+
+use std::sync::OnceLock;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// Embedded at build time; override via the `ZMNG_UPDATE_PUBKEY` env var
+/// for internal/staging builds signed with a different key.
+const UPDATE_PUBKEY: &str = match option_env!("ZMNG_UPDATE_PUBKEY") {
+  Some(key) => key,
+  None => include_str!("../keys/update_minisign.pub"),
+};
+
+/// Override via `ZMNG_UPDATE_ENDPOINT` for staging manifests; defaults
+/// to the production release manifest.
+const DEFAULT_UPDATE_ENDPOINT: &str = "https://zmng.pliablepixels.org/updates/manifest.json";
+
+fn update_endpoint() -> &'static str {
+  static ENDPOINT: OnceLock<String> = OnceLock::new();
+  ENDPOINT.get_or_init(|| {
+    std::env::var("ZMNG_UPDATE_ENDPOINT").unwrap_or_else(|_| DEFAULT_UPDATE_ENDPOINT.to_string())
+  })
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateManifest {
+  version: String,
+  platforms: std::collections::HashMap<String, PlatformEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlatformEntry {
+  url: String,
+  signature: String,
+}
+
+/// Progress emitted to the frontend over the `updater://progress` event
+/// channel while an update archive downloads.
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum UpdateProgress {
+  Checking,
+  UpToDate,
+  Downloading { downloaded_bytes: u64, total_bytes: Option<u64> },
+  Verifying,
+  ReadyToInstall { version: String },
+  Failed { reason: String },
+}
+
+/// Where the last verified-but-not-yet-installed update archive was
+/// staged, so `restart_to_install` knows there's something to apply.
+static PENDING_UPDATE: OnceLock<std::sync::Mutex<Option<std::path::PathBuf>>> = OnceLock::new();
+
+fn pending_update_slot() -> &'static std::sync::Mutex<Option<std::path::PathBuf>> {
+  PENDING_UPDATE.get_or_init(|| std::sync::Mutex::new(None))
+}
+
+#[derive(Debug, thiserror::Error)]
+enum UpdateError {
+  #[error("failed to fetch update manifest: {0}")]
+  Manifest(reqwest::Error),
+  #[error("update manifest is not valid json: {0}")]
+  ManifestParse(#[from] serde_json::Error),
+  #[error("running build version is not valid semver: {0}")]
+  CurrentVersion(semver::Error),
+  #[error("manifest version is not valid semver: {0}")]
+  ManifestVersion(semver::Error),
+  #[error("no update available for this platform")]
+  UnsupportedPlatform,
+  #[error("failed to download update archive: {0}")]
+  Download(reqwest::Error),
+  #[error("downloaded archive failed signature verification")]
+  SignatureInvalid,
+  #[error("embedded update public key is malformed")]
+  InvalidPubkey,
+  #[error("failed to stage the update archive on disk: {0}")]
+  Staging(std::io::Error),
+  #[error("failed to unpack the staged update archive: {0}")]
+  Extract(std::io::Error),
+  #[error("staged update archive did not contain exactly one executable entry")]
+  MalformedArchive,
+  #[error("failed to install the staged update: {0}")]
+  Install(std::io::Error),
+  #[error("no update has been staged")]
+  NothingStaged,
+}
+
+/// Current platform identifier as used in the manifest's `platforms` map.
+fn platform_key() -> &'static str {
+  if cfg!(target_os = "macos") {
+    "darwin"
+  } else if cfg!(target_os = "windows") {
+    "windows"
+  } else {
+    "linux"
+  }
+}
+
+/// Fetches the manifest, and if it names a newer version than the
+/// running build, downloads and verifies the archive for this platform,
+/// reporting progress via `updater://progress`.
+///
+/// The archive is verified against [`UPDATE_PUBKEY`] before it's staged
+/// anywhere on disk; a signature mismatch aborts the update entirely
+/// rather than falling back to an unverified install.
+async fn check_and_stage<R: Runtime>(app: &AppHandle<R>) -> Result<(), UpdateError> {
+  let _ = app.emit("updater://progress", UpdateProgress::Checking);
+
+  let manifest: UpdateManifest = reqwest::get(update_endpoint())
+    .await
+    .map_err(UpdateError::Manifest)?
+    .json()
+    .await
+    .map_err(UpdateError::Manifest)?;
+
+  let current = Version::parse(env!("CARGO_PKG_VERSION")).map_err(UpdateError::CurrentVersion)?;
+  let latest = Version::parse(&manifest.version).map_err(UpdateError::ManifestVersion)?;
+
+  if latest <= current {
+    let _ = app.emit("updater://progress", UpdateProgress::UpToDate);
+    return Ok(());
+  }
+
+  let entry = manifest
+    .platforms
+    .get(platform_key())
+    .ok_or(UpdateError::UnsupportedPlatform)?;
+
+  let archive = download_with_progress(app, &entry.url).await?;
+
+  let _ = app.emit("updater://progress", UpdateProgress::Verifying);
+  verify_signature(&archive, &entry.signature)?;
+
+  // The archive is trusted at this point; write it to the staging
+  // directory and remember where, so `restart_to_install` can hand it to
+  // the platform-specific installer on restart.
+  let staged_path = stage_for_install(app, &archive, &manifest.version)?;
+  *pending_update_slot().lock().unwrap() = Some(staged_path);
+
+  let _ = app.emit(
+    "updater://progress",
+    UpdateProgress::ReadyToInstall { version: manifest.version },
+  );
+  Ok(())
+}
+
+/// Downloads the update archive, emitting incremental progress events so
+/// the frontend can render a progress bar instead of a spinner.
+async fn download_with_progress<R: Runtime>(app: &AppHandle<R>, url: &str) -> Result<Vec<u8>, UpdateError> {
+  let response = reqwest::get(url).await.map_err(UpdateError::Download)?;
+  let total_bytes = response.content_length();
+  let mut downloaded = Vec::new();
+  let mut stream = response;
+
+  while let Some(chunk) = stream.chunk().await.map_err(UpdateError::Download)? {
+    downloaded.extend_from_slice(&chunk);
+    let _ = app.emit(
+      "updater://progress",
+      UpdateProgress::Downloading { downloaded_bytes: downloaded.len() as u64, total_bytes },
+    );
+  }
+
+  Ok(downloaded)
+}
+
+/// Pulls the base64 key out of a minisign public-key file, skipping the
+/// leading `untrusted comment: ...` line that `PublicKey::from_base64`
+/// doesn't expect.
+fn minisign_pubkey_base64(file_contents: &str) -> Result<&str, UpdateError> {
+  file_contents
+    .lines()
+    .map(str::trim)
+    .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+    .ok_or(UpdateError::InvalidPubkey)
+}
+
+/// Verifies `archive` against the base64-encoded minisign signature
+/// using the embedded public key.
+fn verify_signature(archive: &[u8], signature_b64: &str) -> Result<(), UpdateError> {
+  let public_key = minisign_verify::PublicKey::from_base64(minisign_pubkey_base64(UPDATE_PUBKEY)?)
+    .map_err(|_| UpdateError::InvalidPubkey)?;
+  let signature =
+    minisign_verify::Signature::decode(signature_b64).map_err(|_| UpdateError::SignatureInvalid)?;
+  public_key
+    .verify(archive, &signature, false)
+    .map_err(|_| UpdateError::SignatureInvalid)
+}
+
+/// Writes the verified archive to the app's local data directory so
+/// `restart_to_install` can swap it in once the user accepts the
+/// restart prompt, and returns where it landed.
+fn stage_for_install<R: Runtime>(
+  app: &AppHandle<R>,
+  archive: &[u8],
+  version: &str,
+) -> Result<std::path::PathBuf, UpdateError> {
+  let dir = app
+    .path()
+    .app_local_data_dir()
+    .map_err(|_| UpdateError::Staging(std::io::Error::new(std::io::ErrorKind::NotFound, "no app-local data dir")))?
+    .join("pending-update");
+  std::fs::create_dir_all(&dir).map_err(UpdateError::Staging)?;
+
+  let path = dir.join(format!("zmng-{version}.update"));
+  std::fs::write(&path, archive).map_err(UpdateError::Staging)?;
+  Ok(path)
+}
+
+/// `check_for_updates` command, invoked from the frontend's "Check for
+/// updates" menu item.
+#[tauri::command]
+pub async fn check_for_updates<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+  check_and_stage(&app).await.map_err(|err| {
+    let _ = app.emit("updater://progress", UpdateProgress::Failed { reason: err.to_string() });
+    err.to_string()
+  })
+}
+
+/// Unpacks the single executable entry out of a staged update zip,
+/// writing it alongside the archive and returning where it landed.
+///
+/// The release pipeline always packs exactly one entry (the platform
+/// executable) per archive; anything else means a corrupt or
+/// unexpected artifact, which is refused rather than guessed at.
+fn extract_staged_archive(archive_path: &std::path::Path) -> Result<std::path::PathBuf, UpdateError> {
+  let file = std::fs::File::open(archive_path).map_err(UpdateError::Extract)?;
+  let mut zip = zip::ZipArchive::new(file)
+    .map_err(|err| UpdateError::Extract(std::io::Error::new(std::io::ErrorKind::InvalidData, err)))?;
+  if zip.len() != 1 {
+    return Err(UpdateError::MalformedArchive);
+  }
+
+  let mut entry = zip
+    .by_index(0)
+    .map_err(|err| UpdateError::Extract(std::io::Error::new(std::io::ErrorKind::InvalidData, err)))?;
+  let executable_path = archive_path.with_extension("exe-staged");
+  let mut out_file = std::fs::File::create(&executable_path).map_err(UpdateError::Extract)?;
+  std::io::copy(&mut entry, &mut out_file).map_err(UpdateError::Extract)?;
+  drop(out_file);
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(&executable_path, std::fs::Permissions::from_mode(0o755)).map_err(UpdateError::Extract)?;
+  }
+
+  Ok(executable_path)
+}
+
+/// Unpacks the staged archive and replaces the running executable with
+/// it.
+///
+/// There's no separate platform installer step to hand this off to —
+/// the running binary has to swap itself out. `self_replace` does the
+/// per-platform dance that requires (renaming the old binary out of the
+/// way before overwriting on Windows, where the running exe is locked;
+/// a plain overwrite elsewhere), so a restart afterwards actually boots
+/// into the new version instead of re-launching the old one.
+fn install_staged_update(archive_path: &std::path::Path) -> Result<(), UpdateError> {
+  let executable_path = extract_staged_archive(archive_path)?;
+  self_replace::self_replace(&executable_path).map_err(UpdateError::Install)?;
+  let _ = std::fs::remove_file(&executable_path);
+  let _ = std::fs::remove_file(archive_path);
+  Ok(())
+}
+
+/// `restart_to_install` command, invoked from the frontend's restart
+/// prompt once the user accepts installing a staged update.
+///
+/// Installs the staged archive in place of the running executable
+/// before restarting, so the relaunch actually lands on the new
+/// version instead of the old binary re-detecting the same manifest and
+/// re-staging forever.
+#[tauri::command]
+pub fn restart_to_install<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+  let Some(staged_path) = pending_update_slot().lock().unwrap().take() else {
+    return Err(UpdateError::NothingStaged.to_string());
+  };
+  install_staged_update(&staged_path).map_err(|err| err.to_string())?;
+  app.restart();
+}
+
+/// Kicks off the startup update check; the `check_for_updates` command
+/// itself is wired into the shared invoke handler in `lib.rs`.
+pub fn register<R: Runtime>(builder: tauri::Builder<R>) -> tauri::Builder<R> {
+  builder.setup(|app| {
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+      if let Err(err) = check_and_stage(&handle).await {
+        let _ = handle.emit("updater://progress", UpdateProgress::Failed { reason: err.to_string() });
+      }
+    });
+    Ok(())
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn strips_the_untrusted_comment_line() {
+    let file = "untrusted comment: zmNg update signing key\nRWQ_some_base64_key_material\n";
+    assert_eq!(minisign_pubkey_base64(file).unwrap(), "RWQ_some_base64_key_material");
+  }
+
+  #[test]
+  fn rejects_a_pubkey_file_with_only_a_comment() {
+    assert!(minisign_pubkey_base64("untrusted comment: zmNg update signing key\n").is_err());
+  }
+
+  #[test]
+  fn newer_manifest_version_beats_current() {
+    let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+    let newer = Version::new(current.major, current.minor, current.patch + 1);
+    assert!(newer > current);
+  }
+
+  #[test]
+  fn platform_key_matches_running_os() {
+    let key = platform_key();
+    assert!(["darwin", "windows", "linux"].contains(&key));
+  }
+}